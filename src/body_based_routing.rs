@@ -1,57 +1,489 @@
 use envoy_proxy_dynamic_modules_rust_sdk::*;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// A single routing rule: `expr` is compiled into an [`expr::Expr`] at config-parse
+/// time and evaluated against a request [`expr::Context`] at request time. The first
+/// rule whose expression evaluates to `true` wins and its `route_to` value is written
+/// to `x-route-to`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RouteRule {
+    expr: String,
+    route_to: String,
+}
+
+/// A [`RouteRule`] whose expression has already been parsed into an AST, so routing
+/// decisions don't pay parser overhead on every request.
+#[derive(Debug, Clone)]
+pub(crate) struct CompiledRule {
+    expr: expr::Expr,
+    /// Kept alongside the compiled `expr` so a matched rule can be identified
+    /// in the debug decision log without re-stringifying the AST.
+    expr_src: String,
+    route_to: String,
+}
+
+/// What to do when the buffered request body exceeds `max_body_bytes`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum OverflowAction {
+    /// Send a local `413 Payload Too Large` reply and halt iteration.
+    #[default]
+    Reject,
+    /// Set `x-route-to` to a fixed fallback value and `Continue`; `routes` are
+    /// not evaluated against the partial body buffered so far.
+    Route { route_to: String },
+}
+
+fn default_max_body_bytes() -> Option<usize> {
+    Some(64 * 1024)
+}
+
+/// Copies a single computed field from the response body (or headers) onto a
+/// response header. Unlike [`RouteRule`], `expr` need not evaluate to a bool: its
+/// value is stringified and written to `header`, and the header is left unset if
+/// the expression evaluates to `null` or errors.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResponseExtraction {
+    expr: String,
+    header: String,
+}
+
+/// A [`ResponseExtraction`] whose expression has already been parsed into an AST.
+#[derive(Debug, Clone)]
+pub(crate) struct CompiledExtraction {
+    expr: expr::Expr,
+    header: String,
+}
+
+/// A response header to copy from a [`CalloutConfig`] callout response onto a
+/// request header. JSON body fields are copied via `response_fields` instead.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HeaderMapping {
+    from: String,
+    to: String,
+}
+
+/// What to do when a [`CalloutConfig`] callout times out or returns a non-2xx
+/// response.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum CalloutFailureAction {
+    /// Resume with whatever route was already resolved from `routes` (fail open).
+    #[default]
+    Continue,
+    /// Send a local error reply and halt iteration (fail closed).
+    Reject { status: u32 },
+}
+
+fn default_callout_timeout_ms() -> u64 {
+    1000
+}
+
+/// Configuration for an optional outbound HTTP callout issued after request body
+/// parsing, used to enrich or authorize the request before it reaches routing.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CalloutConfig {
+    /// Upstream cluster to call out to.
+    cluster: String,
+    /// Path template with `{...}` placeholders, each resolved as a `body.json.*`
+    /// path against the decoded request body, e.g. `/authz/{user.id}`.
+    path_template: String,
+    #[serde(default = "default_callout_timeout_ms")]
+    timeout_ms: u64,
+    /// Response headers to copy onto the request, keyed by response header name.
+    #[serde(default)]
+    response_headers: Vec<HeaderMapping>,
+    /// Fields to copy from the callout's (JSON) response body onto request
+    /// headers, evaluated the same way as [`ResponseExtraction`].
+    #[serde(default)]
+    response_fields: Vec<ResponseExtraction>,
+    #[serde(default)]
+    on_failure: CalloutFailureAction,
+}
+
+/// A [`CalloutConfig`] whose `path_template` and `response_fields` have already
+/// been compiled.
+#[derive(Debug, Clone)]
+pub(crate) struct CompiledCallout {
+    cluster: String,
+    path_template: Vec<PathTemplateSegment>,
+    timeout_ms: u64,
+    response_headers: Vec<HeaderMapping>,
+    response_fields: Vec<CompiledExtraction>,
+    on_failure: CalloutFailureAction,
+}
+
+/// One piece of a compiled [`CalloutConfig::path_template`]: either literal text
+/// or a `{...}` placeholder compiled into a `body.json.*` expression.
+#[derive(Debug, Clone)]
+pub(crate) enum PathTemplateSegment {
+    Literal(String),
+    Field(expr::Expr),
+}
+
+/// Compiles a `path_template` string into [`PathTemplateSegment`]s, parsing each
+/// `{field.path}` placeholder as a `body.json.field.path` expression.
+fn compile_path_template(template: &str) -> Result<Vec<PathTemplateSegment>, String> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            segments.push(PathTemplateSegment::Literal(rest[..start].to_string()));
+        }
+        let after_brace = &rest[start + 1..];
+        let end = after_brace
+            .find('}')
+            .ok_or_else(|| "unterminated '{' in path_template".to_string())?;
+        let field = expr::parse(&format!("body.json.{}", &after_brace[..end]))?;
+        segments.push(PathTemplateSegment::Field(field));
+        rest = &after_brace[end + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(PathTemplateSegment::Literal(rest.to_string()));
+    }
+    Ok(segments)
+}
+
+/// Percent-encodes `bytes` for safe inclusion inside a single path segment: only
+/// unreserved characters (`ALPHA` / `DIGIT` / `-` / `.` / `_` / `~`) pass through
+/// unescaped, everything else - notably `/`, `\`, and control bytes - is escaped
+/// as `%XX`. This is what keeps a body-controlled field from splicing extra path
+/// segments (e.g. `../`) into a callout's `:path`.
+fn percent_encode_path_segment(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for &b in bytes {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// Resolves a compiled path template against `ctx`, stringifying each `Field`
+/// segment the same way [`ResponseExtraction`] values are stringified and
+/// percent-encoding the result so a body-controlled field can't inject `/` or
+/// other path-control characters into the callout's `:path`. Dots are left
+/// unescaped, so a field whose value is exactly `..` still yields a `..`
+/// segment. A placeholder that resolves to `null` (or errors) contributes nothing.
+fn resolve_path_template(segments: &[PathTemplateSegment], ctx: &expr::Context) -> String {
+    let mut out = String::new();
+    for segment in segments {
+        match segment {
+            PathTemplateSegment::Literal(s) => out.push_str(s),
+            PathTemplateSegment::Field(field_expr) => {
+                if let Some(bytes) = expr::eval(field_expr, ctx).ok().and_then(|v| expr::value_to_header_bytes(&v)) {
+                    out.push_str(&percent_encode_path_segment(&bytes));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// `callout_id` passed to [`EnvoyHttpFilter::send_http_callout`]; this filter
+/// only ever has one callout in flight per request, so a single fixed id is enough
+/// to recognize the matching completion in [`Filter::on_http_callout_done`].
+const CALLOUT_ID: u32 = 1;
 
 /// Configuration for the body-based routing filter.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct FilterConfig {
+    /// When set, `on_request_body` emits a decision log line (matched rule,
+    /// chosen route, body size) for each request body it routes; no line is
+    /// emitted when `on_overflow` fires.
     #[serde(default)]
     debug: bool,
+    /// Rules evaluated in order against the request; the first match wins. When no
+    /// rule matches (or none are configured), the filter falls back to `"echo1"`.
+    #[serde(default)]
+    routes: Vec<RouteRule>,
+    /// Maximum number of request body bytes to buffer before `on_overflow` kicks
+    /// in. Defaults to 64 KiB; set to `null` to buffer without limit.
+    #[serde(default = "default_max_body_bytes")]
+    max_body_bytes: Option<usize>,
+    /// Action taken once `max_body_bytes` is exceeded. Defaults to rejecting the
+    /// request outright, since an unbounded buffer is a memory-exhaustion vector.
+    #[serde(default)]
+    on_overflow: OverflowAction,
+    /// Fields to copy from the upstream response onto response headers. The
+    /// response body is only buffered when this list is non-empty.
+    #[serde(default)]
+    response_extractions: Vec<ResponseExtraction>,
+    /// Wire format to assume for request/response bodies. When unset, the format
+    /// is sniffed from the `content-type` header on each request/response.
+    #[serde(default)]
+    body_format: Option<body_format::BodyFormat>,
+    /// Optional enrichment/authorization callout issued after request body
+    /// parsing, before the routing header is finalized.
+    #[serde(default)]
+    callout: Option<CalloutConfig>,
+    /// `routes` compiled into ASTs, shared (via `Arc`) with every `Filter` instance
+    /// created from this config.
+    #[serde(skip)]
+    compiled_routes: Arc<Vec<CompiledRule>>,
+    /// `response_extractions` compiled into ASTs, shared with every `Filter`.
+    #[serde(skip)]
+    compiled_extractions: Arc<Vec<CompiledExtraction>>,
+    /// `callout` with its `path_template`/`response_fields` compiled, shared with
+    /// every `Filter`.
+    #[serde(skip)]
+    compiled_callout: Option<Arc<CompiledCallout>>,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        FilterConfig {
+            debug: false,
+            routes: Vec::new(),
+            max_body_bytes: default_max_body_bytes(),
+            on_overflow: OverflowAction::default(),
+            response_extractions: Vec::new(),
+            body_format: None,
+            callout: None,
+            compiled_routes: Arc::new(Vec::new()),
+            compiled_extractions: Arc::new(Vec::new()),
+            compiled_callout: None,
+        }
+    }
 }
 
 impl FilterConfig {
     /// Creates a new FilterConfig from JSON configuration.
     pub fn new(filter_config: &str) -> Self {
-        if filter_config.trim().is_empty() {
-            FilterConfig { debug: false }
+        let mut config = if filter_config.trim().is_empty() {
+            FilterConfig::default()
         } else {
-            serde_json::from_str::<FilterConfig>(filter_config)
-                .unwrap_or_else(|_| FilterConfig { debug: false })
-        }
+            serde_json::from_str::<FilterConfig>(filter_config).unwrap_or_default()
+        };
+
+        // Compile each rule's/extraction's expression up front. An expression that
+        // fails to parse is dropped rather than failing filter creation outright.
+        let compiled_routes = config
+            .routes
+            .iter()
+            .filter_map(|rule| {
+                expr::parse(&rule.expr).ok().map(|expr| CompiledRule {
+                    expr,
+                    expr_src: rule.expr.clone(),
+                    route_to: rule.route_to.clone(),
+                })
+            })
+            .collect();
+        config.compiled_routes = Arc::new(compiled_routes);
+
+        let compiled_extractions = config
+            .response_extractions
+            .iter()
+            .filter_map(|extraction| {
+                expr::parse(&extraction.expr).ok().map(|expr| CompiledExtraction {
+                    expr,
+                    header: extraction.header.clone(),
+                })
+            })
+            .collect();
+        config.compiled_extractions = Arc::new(compiled_extractions);
+
+        // An unparseable path_template/response field drops the whole callout
+        // rather than issuing it half-configured.
+        config.compiled_callout = config.callout.as_ref().and_then(|callout| {
+            let path_template = compile_path_template(&callout.path_template).ok()?;
+            let response_fields = callout
+                .response_fields
+                .iter()
+                .map(|extraction| {
+                    expr::parse(&extraction.expr).map(|expr| CompiledExtraction {
+                        expr,
+                        header: extraction.header.clone(),
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .ok()?;
+            Some(Arc::new(CompiledCallout {
+                cluster: callout.cluster.clone(),
+                path_template,
+                timeout_ms: callout.timeout_ms,
+                response_headers: callout.response_headers.clone(),
+                response_fields,
+                on_failure: callout.on_failure.clone(),
+            }))
+        });
+
+        config
     }
 }
 
 impl<EC: EnvoyHttpFilterConfig, EHF: EnvoyHttpFilter> HttpFilterConfig<EC, EHF> for FilterConfig {
     fn new_http_filter(&mut self, _envoy: &mut EC) -> Box<dyn HttpFilter<EHF>> {
-        Box::new(Filter::new())
+        Box::new(Filter::new(
+            self.compiled_routes.clone(),
+            self.max_body_bytes,
+            self.on_overflow.clone(),
+            self.compiled_extractions.clone(),
+            self.body_format,
+            self.compiled_callout.clone(),
+            self.debug,
+        ))
     }
 }
 
 /// Body-based routing filter that analyzes request bodies and sets routing headers.
-/// 
+///
 /// MEMORY CONSIDERATIONS:
-/// - Buffers complete request bodies in memory during analysis
-/// - Memory usage scales with request body size
-/// - Consider implementing body size limits for production use
-/// 
+/// - Buffers the request body in memory during analysis, up to `max_body_bytes`
+/// - Once that limit is exceeded, `on_overflow` fires and buffering stops
+///
 /// LATENCY CONSIDERATIONS:
 /// - Pauses request processing until complete body is available
 /// - JSON parsing adds computational overhead
 /// - Route cache clearing forces re-evaluation (small cost)
-pub struct Filter;
+pub struct Filter {
+    routes: Arc<Vec<CompiledRule>>,
+    headers: Vec<(String, String)>,
+    max_body_bytes: Option<usize>,
+    on_overflow: OverflowAction,
+    /// Set once `on_overflow` has fired, so a stray extra callback can't apply it twice.
+    overflowed: bool,
+    extractions: Arc<Vec<CompiledExtraction>>,
+    response_headers: Vec<(String, String)>,
+    /// Wire format to assume for bodies; `None` means sniff from `content-type`.
+    body_format: Option<body_format::BodyFormat>,
+    callout: Option<Arc<CompiledCallout>>,
+    /// Set once a callout has been dispatched, so a stray/duplicate completion
+    /// callback can't be processed twice.
+    callout_dispatched: bool,
+    /// Mirrors `FilterConfig::debug`; gates the decision log line in
+    /// `on_request_body`.
+    debug: bool,
+}
 
 impl Filter {
-    pub fn new() -> Self {
-        Self
+    pub(crate) fn new(
+        routes: Arc<Vec<CompiledRule>>,
+        max_body_bytes: Option<usize>,
+        on_overflow: OverflowAction,
+        extractions: Arc<Vec<CompiledExtraction>>,
+        body_format: Option<body_format::BodyFormat>,
+        callout: Option<Arc<CompiledCallout>>,
+        debug: bool,
+    ) -> Self {
+        Self {
+            routes,
+            headers: Vec::new(),
+            max_body_bytes,
+            on_overflow,
+            overflowed: false,
+            extractions,
+            response_headers: Vec::new(),
+            body_format,
+            callout,
+            callout_dispatched: false,
+            debug,
+        }
+    }
+
+    /// Emits a single structured `key=value` decision log line when `debug` is
+    /// enabled, so operators can see why a request was routed the way it was
+    /// without dumping body contents (see `logging_passthrough`'s leveled
+    /// logging for the symmetric request/response-body logging filter).
+    fn log_decision(&self, matched_rule: Option<&str>, route_to: &str, body_len: usize) {
+        if !self.debug {
+            return;
+        }
+        eprintln!(
+            "[BODY_TO_HEADER] level=Debug stage=routing_decision matched_rule={} route_to={} body_bytes={}",
+            matched_rule.unwrap_or("none"),
+            route_to,
+            body_len,
+        );
+    }
+
+    /// Evaluates `self.routes` in order against `ctx`, returning the first matching
+    /// rule. Rules that error out or evaluate to anything other than `true` are
+    /// simply skipped.
+    fn resolve_rule<'a>(&'a self, ctx: &expr::Context) -> Option<&'a CompiledRule> {
+        self.routes.iter().find(|rule| matches!(expr::eval(&rule.expr, ctx), Ok(expr::Value::Bool(true))))
+    }
+
+    /// Returns whether the currently buffered request body exceeds `max_body_bytes`.
+    fn body_over_limit<EHF: EnvoyHttpFilter>(&self, envoy_filter: &mut EHF) -> bool {
+        let Some(max) = self.max_body_bytes else {
+            return false;
+        };
+        let buffered_len: usize = envoy_filter
+            .get_request_body()
+            .map(|buffers| buffers.iter().map(|b| b.as_slice().len()).sum())
+            .unwrap_or(0);
+        buffered_len > max
+    }
+
+    /// Applies `self.on_overflow` exactly once: either sets a fallback route header
+    /// and resumes, or sends a local 413 reply and halts iteration.
+    fn apply_overflow<EHF: EnvoyHttpFilter>(
+        &mut self,
+        envoy_filter: &mut EHF,
+    ) -> abi::envoy_dynamic_module_type_on_http_filter_request_body_status {
+        self.overflowed = true;
+        match &self.on_overflow {
+            OverflowAction::Route { route_to } => {
+                envoy_filter.set_request_header("x-route-to", route_to.as_bytes());
+                envoy_filter.clear_route_cache();
+                abi::envoy_dynamic_module_type_on_http_filter_request_body_status::Continue
+            }
+            OverflowAction::Reject => {
+                envoy_filter.send_response(413, Vec::new(), Some(b"Payload Too Large"));
+                abi::envoy_dynamic_module_type_on_http_filter_request_body_status::StopIterationNoBuffer
+            }
+        }
     }
 }
 
+/// Collects a header list from the SDK's `(EnvoyBuffer, EnvoyBuffer)` pairs into
+/// owned, UTF-8 decoded strings (pairs that aren't valid UTF-8 are dropped).
+fn collect_headers(pairs: Vec<(EnvoyBuffer, EnvoyBuffer)>) -> Vec<(String, String)> {
+    pairs
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let key_str = std::str::from_utf8(key.as_slice()).ok()?;
+            let value_str = std::str::from_utf8(value.as_slice()).ok()?;
+            Some((key_str.to_string(), value_str.to_string()))
+        })
+        .collect()
+}
+
+/// Finds the `content-type` header value, case-insensitively.
+fn content_type(headers: &[(String, String)]) -> Option<&str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+        .map(|(_, v)| v.as_str())
+}
+
+/// Decodes a buffered body using `configured_format` if set, otherwise sniffing
+/// the format from `headers`' `content-type`. Returns `None` - falling through to
+/// the default route - if the format is unrecognized or the body doesn't decode.
+fn decode_body(
+    configured_format: Option<body_format::BodyFormat>,
+    headers: &[(String, String)],
+    body_data: &[u8],
+) -> Option<body_format::Value> {
+    let content_type = content_type(headers);
+    let format = configured_format.or_else(|| body_format::sniff_format(content_type?))?;
+    body_format::decode(format, content_type, body_data)
+}
+
 impl<EHF: EnvoyHttpFilter> HttpFilter<EHF> for Filter {
     fn on_request_headers(
         &mut self,
-        _envoy_filter: &mut EHF,
+        envoy_filter: &mut EHF,
         end_of_stream: bool,
     ) -> abi::envoy_dynamic_module_type_on_http_filter_request_headers_status {
-        
+
+        // Capture headers now so request-body-time rule evaluation can reference
+        // them via the `headers.*` namespace.
+        self.headers = collect_headers(envoy_filter.get_request_headers());
+
         // CRITICAL: For requests with bodies, we must pause header processing here.
         // If we don't pause, Envoy will make routing decisions before we can analyze
         // the body content and set our routing header. StopIteration prevents
@@ -59,7 +491,7 @@ impl<EHF: EnvoyHttpFilter> HttpFilter<EHF> for Filter {
         if !end_of_stream {
             return abi::envoy_dynamic_module_type_on_http_filter_request_headers_status::StopIteration;
         }
-        
+
         // No body expected - continue with default routing
         abi::envoy_dynamic_module_type_on_http_filter_request_headers_status::Continue
     }
@@ -69,7 +501,22 @@ impl<EHF: EnvoyHttpFilter> HttpFilter<EHF> for Filter {
         envoy_filter: &mut EHF,
         end_of_stream: bool,
     ) -> abi::envoy_dynamic_module_type_on_http_filter_request_body_status {
-        
+
+        // Once OverflowAction::Route has fired once, every subsequent chunk for
+        // this request must keep resuming without re-entering the buffering
+        // branch below - otherwise buffering silently re-enables itself with no
+        // size check for the rest of the (potentially huge) body.
+        if self.overflowed {
+            return abi::envoy_dynamic_module_type_on_http_filter_request_body_status::Continue;
+        }
+
+        // Check cumulative buffered size on every chunk, not just at end-of-stream,
+        // so we act the moment the limit is crossed instead of buffering the whole
+        // (potentially huge) body first.
+        if self.body_over_limit(envoy_filter) {
+            return self.apply_overflow(envoy_filter);
+        }
+
         // MEMORY OPTIMIZATION: Buffer body chunks until we have the complete body.
         // StopIterationAndBuffer tells Envoy to accumulate all body data before
         // calling us again with end_of_stream=true. This avoids complex state
@@ -77,66 +524,1381 @@ impl<EHF: EnvoyHttpFilter> HttpFilter<EHF> for Filter {
         if !end_of_stream {
             return abi::envoy_dynamic_module_type_on_http_filter_request_body_status::StopIterationAndBuffer;
         }
-        
-        // Default route - most requests go here for optimal performance
-        let mut route_to = "echo1";
-        
+
+        // Default route - used when no rule matches (or none are configured)
+        let mut route_to = "echo1".to_string();
+
+        // If a callout is configured, its path is built from the same parsed body
+        // used to resolve `route_to` above - so it's resolved in the same pass.
+        let mut pending_callout: Option<(Arc<CompiledCallout>, String)> = None;
+
         // PERFORMANCE CRITICAL: Only process body if we have data to avoid unnecessary work
         if let Some(body_buffers) = envoy_filter.get_request_body() {
             let mut body_data = Vec::new();
             for buffer in body_buffers {
                 body_data.extend_from_slice(buffer.as_slice());
             }
-            
-            // LATENCY CONSIDERATION: JSON parsing adds overhead but enables intelligent routing
-            if !body_data.is_empty() {
-                if let Ok(body_str) = std::str::from_utf8(&body_data) {
-                    if body_str.contains("\"method\"") {
-                        if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(body_str) {
-                            if let Some(method) = json_value.get("method").and_then(|m| m.as_str()) {
-                                if method.contains("echo2") {
-                                    route_to = "echo2";
-                                }
-                            }
-                        }
-                    }
-                }
+
+            // LATENCY CONSIDERATION: decoding adds overhead but enables intelligent routing
+            let body = decode_body(self.body_format, &self.headers, &body_data);
+
+            let ctx = expr::Context {
+                body: body.as_ref(),
+                body_len: body_data.len(),
+                headers: &self.headers,
+            };
+
+            let matched_rule = self.resolve_rule(&ctx);
+            if let Some(rule) = matched_rule {
+                route_to = rule.route_to.clone();
+            }
+            self.log_decision(matched_rule.map(|rule| rule.expr_src.as_str()), &route_to, body_data.len());
+
+            if let Some(callout) = &self.callout {
+                let path = resolve_path_template(&callout.path_template, &ctx);
+                pending_callout = Some((callout.clone(), path));
             }
         }
-        
+
         // ROUTING CRITICAL: Set the header that our Envoy route configuration will match against
         envoy_filter.set_request_header("x-route-to", route_to.as_bytes());
-        
+
         // ESSENTIAL: clear_route_cache() forces Envoy to re-evaluate routing decisions
         // after we've set our routing header. Without this call, Envoy may use
         // cached routing decisions made before our header was available, causing
         // requests to be routed incorrectly.
         envoy_filter.clear_route_cache();
-        
+
+        // If a callout is configured, pause and let `on_http_callout_done` resume
+        // the request (with the buffered body preserved, same as the buffering
+        // branch's `StopIterationAndBuffer` above) once its response is in. The route header set
+        // above is a fail-open default the callout response can still override.
+        if let Some((callout, path)) = pending_callout {
+            let dispatched = envoy_filter.send_http_callout(
+                CALLOUT_ID,
+                &callout.cluster,
+                vec![(":method", b"GET".as_slice()), (":path", path.as_bytes())],
+                None,
+                callout.timeout_ms,
+            );
+            if dispatched {
+                self.callout_dispatched = true;
+                return abi::envoy_dynamic_module_type_on_http_filter_request_body_status::StopIterationAndBuffer;
+            }
+            // Dispatch itself failed synchronously (e.g. unknown cluster) - apply
+            // the same on_failure policy as a non-2xx/timeout completion, rather
+            // than always falling open regardless of configuration.
+            return match &callout.on_failure {
+                CalloutFailureAction::Continue => {
+                    abi::envoy_dynamic_module_type_on_http_filter_request_body_status::Continue
+                }
+                CalloutFailureAction::Reject { status } => {
+                    envoy_filter.send_response(*status, Vec::new(), None);
+                    abi::envoy_dynamic_module_type_on_http_filter_request_body_status::StopIterationNoBuffer
+                }
+            };
+        }
+
         // Resume normal request processing with our routing header in place
         abi::envoy_dynamic_module_type_on_http_filter_request_body_status::Continue
     }
+
+    fn on_response_headers(
+        &mut self,
+        envoy_filter: &mut EHF,
+        end_of_stream: bool,
+    ) -> abi::envoy_dynamic_module_type_on_http_filter_response_headers_status {
+        self.response_headers = collect_headers(envoy_filter.get_response_headers());
+
+        // Unlike the request path, there's nothing to pause for unless extractions
+        // are actually configured - most responses should pass straight through.
+        if !end_of_stream && !self.extractions.is_empty() {
+            return abi::envoy_dynamic_module_type_on_http_filter_response_headers_status::StopIteration;
+        }
+
+        abi::envoy_dynamic_module_type_on_http_filter_response_headers_status::Continue
+    }
+
+    fn on_response_body(
+        &mut self,
+        envoy_filter: &mut EHF,
+        end_of_stream: bool,
+    ) -> abi::envoy_dynamic_module_type_on_http_filter_response_body_status {
+        if self.extractions.is_empty() {
+            return abi::envoy_dynamic_module_type_on_http_filter_response_body_status::Continue;
+        }
+
+        if !end_of_stream {
+            return abi::envoy_dynamic_module_type_on_http_filter_response_body_status::StopIterationAndBuffer;
+        }
+
+        if let Some(body_buffers) = envoy_filter.get_response_body() {
+            let mut body_data = Vec::new();
+            for buffer in body_buffers {
+                body_data.extend_from_slice(buffer.as_slice());
+            }
+
+            let body = decode_body(self.body_format, &self.response_headers, &body_data);
+            let ctx = expr::Context {
+                body: body.as_ref(),
+                body_len: body_data.len(),
+                headers: &self.response_headers,
+            };
+
+            for extraction in self.extractions.iter() {
+                let bytes = expr::eval(&extraction.expr, &ctx)
+                    .ok()
+                    .and_then(|value| expr::value_to_header_bytes(&value));
+                if let Some(bytes) = bytes {
+                    envoy_filter.set_response_header(&extraction.header, &bytes);
+                }
+            }
+        }
+
+        abi::envoy_dynamic_module_type_on_http_filter_response_body_status::Continue
+    }
+
+    fn on_http_callout_done(
+        &mut self,
+        envoy_filter: &mut EHF,
+        callout_id: u32,
+        result: abi::envoy_dynamic_module_type_http_callout_result,
+        response_headers: Vec<(EnvoyBuffer, EnvoyBuffer)>,
+        response_body: Option<EnvoyBuffer>,
+    ) {
+        if callout_id != CALLOUT_ID || !self.callout_dispatched {
+            return;
+        }
+        self.callout_dispatched = false;
+
+        let Some(callout) = self.callout.clone() else {
+            envoy_filter.continue_request();
+            return;
+        };
+
+        let response_headers = collect_headers(response_headers);
+        let succeeded = matches!(result, abi::envoy_dynamic_module_type_http_callout_result::Success)
+            && response_headers
+                .iter()
+                .find(|(k, _)| k == ":status")
+                .and_then(|(_, v)| v.parse::<u32>().ok())
+                .is_some_and(|status| (200..300).contains(&status));
+
+        if !succeeded {
+            return match &callout.on_failure {
+                CalloutFailureAction::Continue => envoy_filter.continue_request(),
+                CalloutFailureAction::Reject { status } => envoy_filter.send_response(*status, Vec::new(), None),
+            };
+        }
+
+        for mapping in &callout.response_headers {
+            if let Some((_, value)) = response_headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(&mapping.from)) {
+                envoy_filter.set_request_header(&mapping.to, value.as_bytes());
+            }
+        }
+
+        if !callout.response_fields.is_empty() {
+            let body_data = response_body.map(|b| b.as_slice().to_vec()).unwrap_or_default();
+            let body = decode_body(None, &response_headers, &body_data);
+            let ctx = expr::Context {
+                body: body.as_ref(),
+                body_len: body_data.len(),
+                headers: &response_headers,
+            };
+            for field in &callout.response_fields {
+                let bytes = expr::eval(&field.expr, &ctx).ok().and_then(|v| expr::value_to_header_bytes(&v));
+                if let Some(bytes) = bytes {
+                    envoy_filter.set_request_header(&field.header, &bytes);
+                }
+            }
+        }
+
+        envoy_filter.clear_route_cache();
+        envoy_filter.continue_request();
+    }
+}
+
+/// Decodes request/response bodies of varying wire formats into one uniform tree
+/// that routing rules and extractions can query identically, regardless of
+/// whether the body was actually JSON, form-encoded, multipart, or protobuf.
+mod body_format {
+    use serde::{Deserialize, Serialize};
+
+    /// A decoded body value. Every [`super::body_format`] decoder below normalizes
+    /// its input into this shape.
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Value {
+        Null,
+        Bool(bool),
+        Number(f64),
+        Str(String),
+        Array(Vec<Value>),
+        Object(Vec<(String, Value)>),
+    }
+
+    impl Value {
+        pub fn get_field(&self, name: &str) -> Option<&Value> {
+            match self {
+                Value::Object(pairs) => pairs.iter().find(|(k, _)| k == name).map(|(_, v)| v),
+                _ => None,
+            }
+        }
+
+        /// Indexes by position into an array, or (so tag-numbered protobuf fields
+        /// can be looked up as `body.json[1]`) by decimal key into an object.
+        pub fn get_index(&self, index: usize) -> Option<&Value> {
+            match self {
+                Value::Array(items) => items.get(index),
+                Value::Object(pairs) => pairs.iter().find(|(k, _)| k == &index.to_string()).map(|(_, v)| v),
+                _ => None,
+            }
+        }
+    }
+
+    /// Which decoder to run over a body. When unset on [`super::FilterConfig`],
+    /// the format is sniffed from the request/response `Content-Type` header.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum BodyFormat {
+        Json,
+        FormUrlEncoded,
+        Multipart,
+        Protobuf,
+    }
+
+    /// Sniffs a [`BodyFormat`] from a `Content-Type` header value. Returns `None`
+    /// for unrecognized or absent content types, which callers treat as
+    /// undecodable: routing falls through to the default route.
+    pub fn sniff_format(content_type: &str) -> Option<BodyFormat> {
+        let mime = content_type.split(';').next().unwrap_or("").trim();
+        match mime {
+            "application/json" => Some(BodyFormat::Json),
+            "application/x-www-form-urlencoded" => Some(BodyFormat::FormUrlEncoded),
+            "multipart/form-data" => Some(BodyFormat::Multipart),
+            "application/x-protobuf" | "application/protobuf" => Some(BodyFormat::Protobuf),
+            _ => None,
+        }
+    }
+
+    fn multipart_boundary(content_type: &str) -> Option<&str> {
+        content_type.split(';').skip(1).find_map(|param| {
+            let param = param.trim();
+            param.strip_prefix("boundary=").map(|b| b.trim_matches('"'))
+        })
+    }
+
+    /// Decodes `data` as `format`. Returns `None` if decoding isn't possible (empty
+    /// body, malformed input, or - for multipart - a missing boundary); callers
+    /// fall through to the default route in that case.
+    pub fn decode(format: BodyFormat, content_type: Option<&str>, data: &[u8]) -> Option<Value> {
+        if data.is_empty() {
+            return None;
+        }
+        match format {
+            BodyFormat::Json => decode_json(data),
+            BodyFormat::FormUrlEncoded => decode_form_urlencoded(data),
+            BodyFormat::Multipart => decode_multipart(content_type?, data),
+            BodyFormat::Protobuf => decode_protobuf(data),
+        }
+    }
+
+    fn decode_json(data: &[u8]) -> Option<Value> {
+        let text = std::str::from_utf8(data).ok()?;
+        let json: serde_json::Value = serde_json::from_str(text).ok()?;
+        Some(json_to_value(&json))
+    }
+
+    fn json_to_value(json: &serde_json::Value) -> Value {
+        match json {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(*b),
+            serde_json::Value::Number(n) => Value::Number(n.as_f64().unwrap_or(f64::NAN)),
+            serde_json::Value::String(s) => Value::Str(s.clone()),
+            serde_json::Value::Array(items) => Value::Array(items.iter().map(json_to_value).collect()),
+            serde_json::Value::Object(map) => {
+                Value::Object(map.iter().map(|(k, v)| (k.clone(), json_to_value(v))).collect())
+            }
+        }
+    }
+
+    /// Decodes `%XX` escapes and `+` over raw bytes - never string slicing, since a
+    /// `%` can be immediately followed by a multi-byte UTF-8 character and slicing
+    /// by byte offset would then land off a char boundary and panic. A `%` not
+    /// followed by two ASCII hex digits is kept as a literal byte.
+    fn percent_decode(bytes: &[u8]) -> String {
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'+' => {
+                    out.push(b' ');
+                    i += 1;
+                }
+                b'%' if i + 2 < bytes.len()
+                    && bytes[i + 1].is_ascii_hexdigit()
+                    && bytes[i + 2].is_ascii_hexdigit() =>
+                {
+                    // Safe: both bytes were just checked to be ASCII hex digits.
+                    let hi = (bytes[i + 1] as char).to_digit(16).unwrap() as u8;
+                    let lo = (bytes[i + 2] as char).to_digit(16).unwrap() as u8;
+                    out.push((hi << 4) | lo);
+                    i += 3;
+                }
+                b => {
+                    out.push(b);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    /// Parses into a flat `Object` of string keys to string values; no nesting.
+    fn decode_form_urlencoded(data: &[u8]) -> Option<Value> {
+        let pairs = data
+            .split(|&b| b == b'&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.iter().position(|&b| b == b'=') {
+                Some(idx) => (percent_decode(&pair[..idx]), Value::Str(percent_decode(&pair[idx + 1..]))),
+                None => (percent_decode(pair), Value::Str(String::new())),
+            })
+            .collect();
+        Some(Value::Object(pairs))
+    }
+
+    /// Parts larger than this are assumed to be file uploads (binary) and are
+    /// skipped rather than surfaced as text.
+    const MAX_MULTIPART_FIELD_BYTES: usize = 8 * 1024;
+
+    /// Extracts part names and small text field values into a flat `Object`,
+    /// skipping file uploads (`filename=`) and any oversized part.
+    fn decode_multipart(content_type: &str, data: &[u8]) -> Option<Value> {
+        let boundary = multipart_boundary(content_type)?;
+        let delimiter = format!("--{}", boundary);
+        let text = String::from_utf8_lossy(data);
+        let parts: Vec<&str> = text.split(delimiter.as_str()).collect();
+        let last_index = parts.len().saturating_sub(1);
+
+        let mut fields = Vec::new();
+        for (i, part) in parts.into_iter().enumerate() {
+            let part = part.trim_start_matches("\r\n").trim_end_matches("\r\n");
+            if part.is_empty() || part == "--" {
+                continue;
+            }
+            let Some((headers, body)) = part.split_once("\r\n\r\n").or_else(|| part.split_once("\n\n")) else {
+                continue;
+            };
+            let Some(name) = headers
+                .lines()
+                .find(|line| line.to_ascii_lowercase().starts_with("content-disposition"))
+                .and_then(|line| {
+                    line.split(';').find_map(|param| {
+                        let param = param.trim();
+                        param.strip_prefix("name=").map(|n| n.trim_matches('"').to_string())
+                    })
+                })
+            else {
+                continue;
+            };
+            let is_file = headers.to_ascii_lowercase().contains("filename=");
+            // Splitting on "--boundary" leaves the terminal marker's "--" at the
+            // start of the final segment, which the `part == "--"` check above
+            // skips. A trailing "--" is trimmed from the final segment only; every
+            // other part's "--" is legitimate field content and is preserved.
+            let body = if i == last_index { body.trim_end_matches("--") } else { body };
+            let body = body.trim_end_matches("\r\n");
+            if is_file || body.len() > MAX_MULTIPART_FIELD_BYTES {
+                continue;
+            }
+            fields.push((name, Value::Str(body.to_string())));
+        }
+        Some(Value::Object(fields))
+    }
+
+    fn read_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+        let mut result: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = *data.get(*pos)?;
+            *pos += 1;
+            result |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(result);
+            }
+            shift += 7;
+            if shift >= 64 {
+                return None;
+            }
+        }
+    }
+
+    /// Reads top-level fields from a length-delimited protobuf message by tag
+    /// number, without a `.proto` schema: each field becomes an entry keyed by its
+    /// decimal tag number (field 1 -> key `"1"`). Length-delimited values that
+    /// decode as valid UTF-8 become strings, other length-delimited values become
+    /// `Null`, and parsing stops at the first field with an unknown wire type.
+    fn decode_protobuf(data: &[u8]) -> Option<Value> {
+        let mut fields = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            let key = read_varint(data, &mut pos)?;
+            let field_number = key >> 3;
+            let wire_type = key & 0x7;
+            let value = match wire_type {
+                0 => Value::Number(read_varint(data, &mut pos)? as f64),
+                1 => {
+                    let bytes: [u8; 8] = data.get(pos..pos + 8)?.try_into().ok()?;
+                    pos += 8;
+                    Value::Number(f64::from_le_bytes(bytes))
+                }
+                2 => {
+                    let len = read_varint(data, &mut pos)? as usize;
+                    let bytes = data.get(pos..pos + len)?;
+                    pos += len;
+                    match std::str::from_utf8(bytes) {
+                        Ok(s) => Value::Str(s.to_string()),
+                        Err(_) => Value::Null,
+                    }
+                }
+                5 => {
+                    let bytes: [u8; 4] = data.get(pos..pos + 4)?.try_into().ok()?;
+                    pos += 4;
+                    Value::Number(f32::from_le_bytes(bytes) as f64)
+                }
+                // Unknown wire type: we can't know its length without a schema, so
+                // stop rather than risk misparsing the rest of the message.
+                _ => return Some(Value::Object(fields)),
+            };
+            fields.push((field_number.to_string(), value));
+        }
+        Some(Value::Object(fields))
+    }
+}
+
+/// A minimal CEL-style expression engine used to evaluate [`RouteRule`]s against
+/// request attributes, modeled on the expression support in Envoy's rate-limit
+/// descriptor extension.
+///
+/// Supported grammar: field access with dotted/indexed paths (`body.json.user.tier`,
+/// `body.json.items[0]`, `headers["x-tier"]`, `body.len`), string/number/bool
+/// literals, the operators `==`, `!=`, `<`, `>`, `&&`, `||`, `!`, and the functions
+/// `contains(a, b)` and `startsWith(a, b)`.
+///
+/// Evaluation is total: a path that doesn't resolve yields [`Value::Null`] rather
+/// than an error, and only type errors (e.g. comparing a string to a number with
+/// `<`) or unknown functions produce [`Err`].
+///
+/// `body.json` is a historical name: it addresses the decoded body view produced
+/// by [`super::body_format`], not literally JSON-only any more.
+mod expr {
+    use super::body_format;
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Value {
+        Null,
+        Bool(bool),
+        Number(f64),
+        Str(String),
+    }
+
+    #[derive(Debug, Clone)]
+    pub enum PathSegment {
+        Field(String),
+        Index(Box<Expr>),
+    }
+
+    #[derive(Debug, Clone)]
+    pub enum Expr {
+        Null,
+        Bool(bool),
+        Number(f64),
+        Str(String),
+        Path(Vec<PathSegment>),
+        Not(Box<Expr>),
+        And(Box<Expr>, Box<Expr>),
+        Or(Box<Expr>, Box<Expr>),
+        Eq(Box<Expr>, Box<Expr>),
+        Ne(Box<Expr>, Box<Expr>),
+        Lt(Box<Expr>, Box<Expr>),
+        Gt(Box<Expr>, Box<Expr>),
+        Call(String, Vec<Expr>),
+    }
+
+    /// Request attributes an [`Expr`] can be evaluated against.
+    pub struct Context<'a> {
+        pub body: Option<&'a body_format::Value>,
+        pub body_len: usize,
+        pub headers: &'a [(String, String)],
+    }
+
+    // ---- Lexer ----
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Tok {
+        Ident(String),
+        Number(f64),
+        Str(String),
+        Dot,
+        Comma,
+        LParen,
+        RParen,
+        LBracket,
+        RBracket,
+        EqEq,
+        NotEq,
+        Lt,
+        Gt,
+        AndAnd,
+        OrOr,
+        Bang,
+        Eof,
+    }
+
+    fn tokenize(input: &str) -> Result<Vec<Tok>, String> {
+        let chars: Vec<char> = input.chars().collect();
+        let mut i = 0;
+        let mut toks = Vec::new();
+
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                ' ' | '\t' | '\n' | '\r' => i += 1,
+                '.' => {
+                    toks.push(Tok::Dot);
+                    i += 1;
+                }
+                ',' => {
+                    toks.push(Tok::Comma);
+                    i += 1;
+                }
+                '(' => {
+                    toks.push(Tok::LParen);
+                    i += 1;
+                }
+                ')' => {
+                    toks.push(Tok::RParen);
+                    i += 1;
+                }
+                '[' => {
+                    toks.push(Tok::LBracket);
+                    i += 1;
+                }
+                ']' => {
+                    toks.push(Tok::RBracket);
+                    i += 1;
+                }
+                '!' => {
+                    if chars.get(i + 1) == Some(&'=') {
+                        toks.push(Tok::NotEq);
+                        i += 2;
+                    } else {
+                        toks.push(Tok::Bang);
+                        i += 1;
+                    }
+                }
+                '=' => {
+                    if chars.get(i + 1) == Some(&'=') {
+                        toks.push(Tok::EqEq);
+                        i += 2;
+                    } else {
+                        return Err(format!("unexpected '=' at position {}", i));
+                    }
+                }
+                '<' => {
+                    toks.push(Tok::Lt);
+                    i += 1;
+                }
+                '>' => {
+                    toks.push(Tok::Gt);
+                    i += 1;
+                }
+                '&' => {
+                    if chars.get(i + 1) == Some(&'&') {
+                        toks.push(Tok::AndAnd);
+                        i += 2;
+                    } else {
+                        return Err(format!("unexpected '&' at position {}", i));
+                    }
+                }
+                '|' => {
+                    if chars.get(i + 1) == Some(&'|') {
+                        toks.push(Tok::OrOr);
+                        i += 2;
+                    } else {
+                        return Err(format!("unexpected '|' at position {}", i));
+                    }
+                }
+                '"' | '\'' => {
+                    let quote = c;
+                    let mut s = String::new();
+                    i += 1;
+                    while i < chars.len() && chars[i] != quote {
+                        s.push(chars[i]);
+                        i += 1;
+                    }
+                    if i >= chars.len() {
+                        return Err("unterminated string literal".to_string());
+                    }
+                    i += 1;
+                    toks.push(Tok::Str(s));
+                }
+                c if c.is_ascii_digit() => {
+                    let start = i;
+                    while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                        i += 1;
+                    }
+                    let s: String = chars[start..i].iter().collect();
+                    let n = s.parse::<f64>().map_err(|_| format!("invalid number '{}'", s))?;
+                    toks.push(Tok::Number(n));
+                }
+                c if c.is_alphabetic() || c == '_' => {
+                    let start = i;
+                    while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                        i += 1;
+                    }
+                    let s: String = chars[start..i].iter().collect();
+                    toks.push(Tok::Ident(s));
+                }
+                _ => return Err(format!("unexpected character '{}' at position {}", c, i)),
+            }
+        }
+
+        toks.push(Tok::Eof);
+        Ok(toks)
+    }
+
+    // ---- Parser (recursive descent, lowest to highest precedence: || && == != < > unary primary) ----
+
+    struct Parser {
+        toks: Vec<Tok>,
+        pos: usize,
+    }
+
+    impl Parser {
+        fn peek(&self) -> &Tok {
+            &self.toks[self.pos]
+        }
+
+        fn advance(&mut self) -> Tok {
+            let t = self.toks[self.pos].clone();
+            if self.pos + 1 < self.toks.len() {
+                self.pos += 1;
+            }
+            t
+        }
+
+        fn expect(&mut self, tok: &Tok) -> Result<(), String> {
+            if self.peek() == tok {
+                self.advance();
+                Ok(())
+            } else {
+                Err(format!("expected {:?}, found {:?}", tok, self.peek()))
+            }
+        }
+
+        fn parse_or(&mut self) -> Result<Expr, String> {
+            let mut lhs = self.parse_and()?;
+            while *self.peek() == Tok::OrOr {
+                self.advance();
+                let rhs = self.parse_and()?;
+                lhs = Expr::Or(Box::new(lhs), Box::new(rhs));
+            }
+            Ok(lhs)
+        }
+
+        fn parse_and(&mut self) -> Result<Expr, String> {
+            let mut lhs = self.parse_equality()?;
+            while *self.peek() == Tok::AndAnd {
+                self.advance();
+                let rhs = self.parse_equality()?;
+                lhs = Expr::And(Box::new(lhs), Box::new(rhs));
+            }
+            Ok(lhs)
+        }
+
+        fn parse_equality(&mut self) -> Result<Expr, String> {
+            let mut lhs = self.parse_relational()?;
+            loop {
+                match self.peek() {
+                    Tok::EqEq => {
+                        self.advance();
+                        let rhs = self.parse_relational()?;
+                        lhs = Expr::Eq(Box::new(lhs), Box::new(rhs));
+                    }
+                    Tok::NotEq => {
+                        self.advance();
+                        let rhs = self.parse_relational()?;
+                        lhs = Expr::Ne(Box::new(lhs), Box::new(rhs));
+                    }
+                    _ => break,
+                }
+            }
+            Ok(lhs)
+        }
+
+        fn parse_relational(&mut self) -> Result<Expr, String> {
+            let mut lhs = self.parse_unary()?;
+            loop {
+                match self.peek() {
+                    Tok::Lt => {
+                        self.advance();
+                        let rhs = self.parse_unary()?;
+                        lhs = Expr::Lt(Box::new(lhs), Box::new(rhs));
+                    }
+                    Tok::Gt => {
+                        self.advance();
+                        let rhs = self.parse_unary()?;
+                        lhs = Expr::Gt(Box::new(lhs), Box::new(rhs));
+                    }
+                    _ => break,
+                }
+            }
+            Ok(lhs)
+        }
+
+        fn parse_unary(&mut self) -> Result<Expr, String> {
+            if *self.peek() == Tok::Bang {
+                self.advance();
+                let inner = self.parse_unary()?;
+                return Ok(Expr::Not(Box::new(inner)));
+            }
+            self.parse_primary()
+        }
+
+        fn parse_primary(&mut self) -> Result<Expr, String> {
+            match self.advance() {
+                Tok::Number(n) => Ok(Expr::Number(n)),
+                Tok::Str(s) => Ok(Expr::Str(s)),
+                Tok::LParen => {
+                    let inner = self.parse_or()?;
+                    self.expect(&Tok::RParen)?;
+                    Ok(inner)
+                }
+                Tok::Ident(name) => match name.as_str() {
+                    "true" => Ok(Expr::Bool(true)),
+                    "false" => Ok(Expr::Bool(false)),
+                    "null" => Ok(Expr::Null),
+                    _ if *self.peek() == Tok::LParen => self.parse_call(name),
+                    _ => self.parse_path(name),
+                },
+                other => Err(format!("unexpected token {:?}", other)),
+            }
+        }
+
+        fn parse_call(&mut self, name: String) -> Result<Expr, String> {
+            self.expect(&Tok::LParen)?;
+            let mut args = Vec::new();
+            if *self.peek() != Tok::RParen {
+                loop {
+                    args.push(self.parse_or()?);
+                    if *self.peek() == Tok::Comma {
+                        self.advance();
+                    } else {
+                        break;
+                    }
+                }
+            }
+            self.expect(&Tok::RParen)?;
+            Ok(Expr::Call(name, args))
+        }
+
+        fn parse_path(&mut self, root: String) -> Result<Expr, String> {
+            let mut segments = vec![PathSegment::Field(root)];
+            loop {
+                match self.peek() {
+                    Tok::Dot => {
+                        self.advance();
+                        match self.advance() {
+                            Tok::Ident(name) => segments.push(PathSegment::Field(name)),
+                            other => return Err(format!("expected field name, found {:?}", other)),
+                        }
+                    }
+                    Tok::LBracket => {
+                        self.advance();
+                        let index = self.parse_or()?;
+                        self.expect(&Tok::RBracket)?;
+                        segments.push(PathSegment::Index(Box::new(index)));
+                    }
+                    _ => break,
+                }
+            }
+            Ok(Expr::Path(segments))
+        }
+    }
+
+    /// Parses `input` into an [`Expr`] AST. Called once per rule at config-parse time.
+    pub fn parse(input: &str) -> Result<Expr, String> {
+        let toks = tokenize(input)?;
+        let mut parser = Parser { toks, pos: 0 };
+        let expr = parser.parse_or()?;
+        if *parser.peek() != Tok::Eof {
+            return Err(format!("unexpected trailing token {:?}", parser.peek()));
+        }
+        Ok(expr)
+    }
+
+    fn body_to_value(v: &body_format::Value) -> Value {
+        match v {
+            body_format::Value::Null => Value::Null,
+            body_format::Value::Bool(b) => Value::Bool(*b),
+            body_format::Value::Number(n) => Value::Number(*n),
+            body_format::Value::Str(s) => Value::Str(s.clone()),
+            // Arrays/objects have no scalar representation; treat as absent.
+            body_format::Value::Array(_) | body_format::Value::Object(_) => Value::Null,
+        }
+    }
+
+    fn json_path(mut value: &body_format::Value, segments: &[PathSegment], ctx: &Context) -> Result<Value, String> {
+        for segment in segments {
+            match segment {
+                PathSegment::Field(name) => match value.get_field(name) {
+                    Some(next) => value = next,
+                    None => return Ok(Value::Null),
+                },
+                PathSegment::Index(index_expr) => {
+                    // A string index (`body.json["x-tier"]`) does an object field
+                    // lookup, same as `headers["..."]` - this is the only way to
+                    // address a JSON field whose name isn't a valid bare identifier
+                    // (contains `-`, starts with a digit, etc). A numeric index
+                    // still does array/positional (or protobuf tag) lookup.
+                    let next = match eval(index_expr, ctx)? {
+                        Value::Str(key) => value.get_field(&key),
+                        Value::Number(n) => value.get_index(n as usize),
+                        _ => return Ok(Value::Null),
+                    };
+                    match next {
+                        Some(next) => value = next,
+                        None => return Ok(Value::Null),
+                    }
+                }
+            }
+        }
+        Ok(body_to_value(value))
+    }
+
+    fn eval_path(segments: &[PathSegment], ctx: &Context) -> Result<Value, String> {
+        let root = match segments.first() {
+            Some(PathSegment::Field(name)) => name.as_str(),
+            _ => return Ok(Value::Null),
+        };
+
+        match root {
+            "body" => match segments.get(1) {
+                Some(PathSegment::Field(name)) if name == "len" => Ok(Value::Number(ctx.body_len as f64)),
+                Some(PathSegment::Field(name)) if name == "json" => match ctx.body {
+                    Some(body) => json_path(body, &segments[2..], ctx),
+                    None => Ok(Value::Null),
+                },
+                _ => Ok(Value::Null),
+            },
+            "headers" => {
+                let key = match segments.get(1) {
+                    Some(PathSegment::Field(name)) => name.clone(),
+                    Some(PathSegment::Index(index_expr)) => match eval(index_expr, ctx)? {
+                        Value::Str(s) => s,
+                        _ => return Ok(Value::Null),
+                    },
+                    None => return Ok(Value::Null),
+                };
+                Ok(ctx
+                    .headers
+                    .iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case(&key))
+                    .map(|(_, v)| Value::Str(v.clone()))
+                    .unwrap_or(Value::Null))
+            }
+            _ => Ok(Value::Null),
+        }
+    }
+
+    /// Stringifies `value` for use as a header value. Returns `None` for
+    /// [`Value::Null`], since there's nothing meaningful to write.
+    pub fn value_to_header_bytes(value: &Value) -> Option<Vec<u8>> {
+        match value {
+            Value::Null => None,
+            Value::Bool(b) => Some(b.to_string().into_bytes()),
+            Value::Number(n) => Some(n.to_string().into_bytes()),
+            Value::Str(s) => Some(s.clone().into_bytes()),
+        }
+    }
+
+    /// Evaluates `expr` against `ctx`. Unresolvable paths yield [`Value::Null`]
+    /// (never an error); only genuine type errors (incompatible comparisons, unknown
+    /// functions, wrong arity) produce [`Err`].
+    pub fn eval(expr: &Expr, ctx: &Context) -> Result<Value, String> {
+        match expr {
+            Expr::Null => Ok(Value::Null),
+            Expr::Bool(b) => Ok(Value::Bool(*b)),
+            Expr::Number(n) => Ok(Value::Number(*n)),
+            Expr::Str(s) => Ok(Value::Str(s.clone())),
+            Expr::Path(segments) => eval_path(segments, ctx),
+            Expr::Not(inner) => match eval(inner, ctx)? {
+                Value::Bool(b) => Ok(Value::Bool(!b)),
+                _ => Err("'!' requires a bool operand".to_string()),
+            },
+            Expr::And(lhs, rhs) => match (eval(lhs, ctx)?, eval(rhs, ctx)?) {
+                (Value::Bool(a), Value::Bool(b)) => Ok(Value::Bool(a && b)),
+                _ => Err("'&&' requires bool operands".to_string()),
+            },
+            Expr::Or(lhs, rhs) => match (eval(lhs, ctx)?, eval(rhs, ctx)?) {
+                (Value::Bool(a), Value::Bool(b)) => Ok(Value::Bool(a || b)),
+                _ => Err("'||' requires bool operands".to_string()),
+            },
+            Expr::Eq(lhs, rhs) => Ok(Value::Bool(eval(lhs, ctx)? == eval(rhs, ctx)?)),
+            Expr::Ne(lhs, rhs) => Ok(Value::Bool(eval(lhs, ctx)? != eval(rhs, ctx)?)),
+            Expr::Lt(lhs, rhs) => match (eval(lhs, ctx)?, eval(rhs, ctx)?) {
+                (Value::Number(a), Value::Number(b)) => Ok(Value::Bool(a < b)),
+                (Value::Str(a), Value::Str(b)) => Ok(Value::Bool(a < b)),
+                _ => Err("'<' requires two numbers or two strings".to_string()),
+            },
+            Expr::Gt(lhs, rhs) => match (eval(lhs, ctx)?, eval(rhs, ctx)?) {
+                (Value::Number(a), Value::Number(b)) => Ok(Value::Bool(a > b)),
+                (Value::Str(a), Value::Str(b)) => Ok(Value::Bool(a > b)),
+                _ => Err("'>' requires two numbers or two strings".to_string()),
+            },
+            Expr::Call(name, args) => eval_call(name, args, ctx),
+        }
+    }
+
+    fn eval_call(name: &str, args: &[Expr], ctx: &Context) -> Result<Value, String> {
+        match name {
+            "contains" => {
+                let [a, b] = args_pair(name, args)?;
+                match (eval(a, ctx)?, eval(b, ctx)?) {
+                    (Value::Str(haystack), Value::Str(needle)) => Ok(Value::Bool(haystack.contains(&needle))),
+                    _ => Err("contains(a, b) requires two strings".to_string()),
+                }
+            }
+            "startsWith" => {
+                let [a, b] = args_pair(name, args)?;
+                match (eval(a, ctx)?, eval(b, ctx)?) {
+                    (Value::Str(haystack), Value::Str(prefix)) => Ok(Value::Bool(haystack.starts_with(&prefix))),
+                    _ => Err("startsWith(a, b) requires two strings".to_string()),
+                }
+            }
+            _ => Err(format!("unknown function '{}'", name)),
+        }
+    }
+
+    fn args_pair<'a>(name: &str, args: &'a [Expr]) -> Result<[&'a Expr; 2], String> {
+        match args {
+            [a, b] => Ok([a, b]),
+            _ => Err(format!("{}() expects exactly 2 arguments", name)),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn filter_from(config: &FilterConfig) -> Filter {
+        Filter::new(
+            config.compiled_routes.clone(),
+            config.max_body_bytes,
+            config.on_overflow.clone(),
+            config.compiled_extractions.clone(),
+            config.body_format,
+            config.compiled_callout.clone(),
+            config.debug,
+        )
+    }
+
+    fn json_body(json: &str) -> body_format::Value {
+        body_format::decode(body_format::BodyFormat::Json, None, json.as_bytes()).unwrap()
+    }
+
+    /// Minimal stand-in for the Envoy-provided `EHF`, just enough to drive
+    /// `Filter::on_request_body` across several chunks in a test.
+    #[derive(Default)]
+    struct MockEnvoyHttpFilter {
+        request_body: Vec<u8>,
+        request_headers: Vec<(String, String)>,
+    }
+
+    impl EnvoyHttpFilter for MockEnvoyHttpFilter {
+        fn get_request_headers(&mut self) -> Vec<(EnvoyBuffer, EnvoyBuffer)> {
+            self.request_headers
+                .iter()
+                .map(|(k, v)| (EnvoyBuffer::new(k.clone().into_bytes()), EnvoyBuffer::new(v.clone().into_bytes())))
+                .collect()
+        }
+        fn get_request_body(&mut self) -> Option<Vec<EnvoyBuffer>> {
+            Some(vec![EnvoyBuffer::new(self.request_body.clone())])
+        }
+        fn get_request_trailers(&mut self) -> Vec<(EnvoyBuffer, EnvoyBuffer)> {
+            Vec::new()
+        }
+        fn get_response_headers(&mut self) -> Vec<(EnvoyBuffer, EnvoyBuffer)> {
+            Vec::new()
+        }
+        fn get_response_body(&mut self) -> Option<Vec<EnvoyBuffer>> {
+            None
+        }
+        fn get_response_trailers(&mut self) -> Vec<(EnvoyBuffer, EnvoyBuffer)> {
+            Vec::new()
+        }
+        fn set_request_header(&mut self, _name: &str, _value: &[u8]) -> bool {
+            true
+        }
+        fn set_response_header(&mut self, _name: &str, _value: &[u8]) -> bool {
+            true
+        }
+        fn clear_route_cache(&mut self) {}
+        fn send_response(&mut self, _status: u32, _headers: Vec<(&str, &[u8])>, _body: Option<&[u8]>) {}
+        fn send_http_callout(
+            &mut self,
+            _callout_id: u32,
+            _cluster_name: &str,
+            _headers: Vec<(&str, &[u8])>,
+            _body: Option<&[u8]>,
+            _timeout_ms: u64,
+        ) -> bool {
+            false
+        }
+        fn continue_request(&mut self) {}
+    }
+
     #[test]
     fn test_filter_config() {
         let config = FilterConfig::new(r#"{"debug": true}"#);
         assert_eq!(config.debug, true);
-        
+
         let config = FilterConfig::new("");
         assert_eq!(config.debug, false);
-        
+
         let config = FilterConfig::new("invalid json");
         assert_eq!(config.debug, false);
     }
 
     #[test]
     fn test_filter_creation() {
-        let _filter = Filter::new();
+        let _filter = filter_from(&FilterConfig::default());
         // Filter creation should succeed
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_filter_config_defaults_max_body_bytes_and_overflow_action() {
+        let config = FilterConfig::new("");
+        assert_eq!(config.max_body_bytes, Some(64 * 1024));
+        assert!(matches!(config.on_overflow, OverflowAction::Reject));
+    }
+
+    #[test]
+    fn test_filter_config_parses_overflow_route() {
+        let config = FilterConfig::new(
+            r#"{"max_body_bytes": 10, "on_overflow": {"action": "route", "route_to": "too-big"}}"#,
+        );
+        assert_eq!(config.max_body_bytes, Some(10));
+        match config.on_overflow {
+            OverflowAction::Route { route_to } => assert_eq!(route_to, "too-big"),
+            OverflowAction::Reject => panic!("expected Route"),
+        }
+    }
+
+    #[test]
+    fn test_overflow_stays_applied_and_does_not_resume_buffering_on_later_chunks() {
+        let config = FilterConfig::new(
+            r#"{"max_body_bytes": 5, "on_overflow": {"action": "route", "route_to": "too-big"}}"#,
+        );
+        let mut filter = filter_from(&config);
+        let mut envoy = MockEnvoyHttpFilter::default();
+
+        // First over-limit chunk: on_overflow fires and resumes immediately.
+        envoy.request_body = b"123456".to_vec();
+        assert_eq!(
+            filter.on_request_body(&mut envoy, false),
+            abi::envoy_dynamic_module_type_on_http_filter_request_body_status::Continue
+        );
+
+        // A later, non-final chunk must keep resuming rather than silently
+        // re-entering StopIterationAndBuffer with no size check.
+        assert_eq!(
+            filter.on_request_body(&mut envoy, false),
+            abi::envoy_dynamic_module_type_on_http_filter_request_body_status::Continue
+        );
+    }
+
+    #[test]
+    fn test_routes_compile_and_match_first_rule() {
+        let config = FilterConfig::new(
+            r#"{"routes": [
+                {"expr": "body.json.method == \"echo2\"", "route_to": "echo2"},
+                {"expr": "body.json.user.tier == \"gold\" && headers[\"x-beta\"] == \"true\"", "route_to": "echo3"}
+            ]}"#,
+        );
+        assert_eq!(config.compiled_routes.len(), 2);
+
+        let body = json_body(r#"{"method": "echo2"}"#);
+        let ctx = expr::Context {
+            body: Some(&body),
+            body_len: 10,
+            headers: &[],
+        };
+        let filter = filter_from(&config);
+        assert_eq!(filter.resolve_rule(&ctx).map(|r| r.route_to.as_str()), Some("echo2"));
+    }
+
+    #[test]
+    fn test_rule_with_unresolvable_path_is_skipped() {
+        let config = FilterConfig::new(
+            r#"{"routes": [{"expr": "body.json.missing.field == \"x\"", "route_to": "echo2"}]}"#,
+        );
+        let body = json_body(r#"{"method": "echo1"}"#);
+        let ctx = expr::Context {
+            body: Some(&body),
+            body_len: 10,
+            headers: &[],
+        };
+        let filter = filter_from(&config);
+        assert_eq!(filter.resolve_rule(&ctx).map(|r| r.route_to.as_str()), None);
+    }
+
+    #[test]
+    fn test_contains_and_starts_with() {
+        let parsed = expr::parse(r#"contains(body.json.method, "ech") && startsWith(body.json.method, "echo")"#)
+            .expect("should parse");
+        let body = json_body(r#"{"method": "echo2"}"#);
+        let ctx = expr::Context {
+            body: Some(&body),
+            body_len: 0,
+            headers: &[],
+        };
+        assert_eq!(expr::eval(&parsed, &ctx), Ok(expr::Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_string_bracket_index_looks_up_json_field_by_name() {
+        // "x-tier" isn't a valid bare identifier, so it can only be addressed via
+        // bracket indexing - same mechanism as headers["..."].
+        let parsed = expr::parse(r#"body.json["x-tier"]"#).expect("should parse");
+        let body = json_body(r#"{"x-tier": "gold"}"#);
+        let ctx = expr::Context {
+            body: Some(&body),
+            body_len: 0,
+            headers: &[],
+        };
+        assert_eq!(expr::eval(&parsed, &ctx), Ok(expr::Value::Str("gold".to_string())));
+    }
+
+    #[test]
+    fn test_response_extraction_compiles_and_resolves_field() {
+        let config = FilterConfig::new(
+            r#"{"response_extractions": [{"expr": "body.json.status", "header": "x-result-status"}]}"#,
+        );
+        assert_eq!(config.compiled_extractions.len(), 1);
+
+        let body = json_body(r#"{"status": "ok"}"#);
+        let ctx = expr::Context {
+            body: Some(&body),
+            body_len: 0,
+            headers: &[],
+        };
+        let extraction = &config.compiled_extractions[0];
+        let value = expr::eval(&extraction.expr, &ctx).unwrap();
+        assert_eq!(expr::value_to_header_bytes(&value), Some(b"ok".to_vec()));
+    }
+
+    #[test]
+    fn test_response_extraction_skips_null_field() {
+        let config = FilterConfig::new(
+            r#"{"response_extractions": [{"expr": "body.json.missing", "header": "x-result-status"}]}"#,
+        );
+        let body = json_body(r#"{"status": "ok"}"#);
+        let ctx = expr::Context {
+            body: Some(&body),
+            body_len: 0,
+            headers: &[],
+        };
+        let extraction = &config.compiled_extractions[0];
+        let value = expr::eval(&extraction.expr, &ctx).unwrap();
+        assert_eq!(expr::value_to_header_bytes(&value), None);
+    }
+
+    #[test]
+    fn test_sniff_format_from_content_type() {
+        assert_eq!(
+            body_format::sniff_format("application/json; charset=utf-8"),
+            Some(body_format::BodyFormat::Json)
+        );
+        assert_eq!(
+            body_format::sniff_format("application/x-www-form-urlencoded"),
+            Some(body_format::BodyFormat::FormUrlEncoded)
+        );
+        assert_eq!(body_format::sniff_format("text/plain"), None);
+    }
+
+    #[test]
+    fn test_decode_form_urlencoded() {
+        let value = body_format::decode(
+            body_format::BodyFormat::FormUrlEncoded,
+            None,
+            b"user%5Btier%5D=gold&q=a+b",
+        )
+        .unwrap();
+        assert_eq!(value.get_field("user[tier]"), Some(&body_format::Value::Str("gold".to_string())));
+        assert_eq!(value.get_field("q"), Some(&body_format::Value::Str("a b".to_string())));
+    }
+
+    #[test]
+    fn test_decode_form_urlencoded_does_not_panic_on_percent_before_multibyte_utf8() {
+        // A literal '%' immediately followed by a multi-byte UTF-8 character must
+        // not be treated as the start of a %XX escape by byte-slicing, since that
+        // can land off a char boundary.
+        let value = body_format::decode(body_format::BodyFormat::FormUrlEncoded, None, "q=a%€b".as_bytes())
+            .unwrap();
+        assert_eq!(value.get_field("q"), Some(&body_format::Value::Str("a%€b".to_string())));
+    }
+
+    #[test]
+    fn test_decode_multipart_skips_file_parts() {
+        let content_type = "multipart/form-data; boundary=XYZ";
+        let body = b"--XYZ\r\nContent-Disposition: form-data; name=\"tier\"\r\n\r\ngold\r\n--XYZ\r\nContent-Disposition: form-data; name=\"avatar\"; filename=\"a.png\"\r\n\r\nbinarydata\r\n--XYZ--\r\n";
+        let value = body_format::decode(body_format::BodyFormat::Multipart, Some(content_type), body).unwrap();
+        assert_eq!(value.get_field("tier"), Some(&body_format::Value::Str("gold".to_string())));
+        assert_eq!(value.get_field("avatar"), None);
+    }
+
+    #[test]
+    fn test_decode_multipart_preserves_field_value_ending_in_double_dash() {
+        // A field whose legitimate value happens to end in "--" must not be
+        // corrupted by the terminal-boundary "--" stripping.
+        let content_type = "multipart/form-data; boundary=XYZ";
+        let body = b"--XYZ\r\nContent-Disposition: form-data; name=\"score\"\r\n\r\nscore--\r\n--XYZ--\r\n";
+        let value = body_format::decode(body_format::BodyFormat::Multipart, Some(content_type), body).unwrap();
+        assert_eq!(value.get_field("score"), Some(&body_format::Value::Str("score--".to_string())));
+    }
+
+    #[test]
+    fn test_decode_protobuf_reads_fields_by_tag_number() {
+        // field 1 (varint, wire type 0) = 150, field 2 (length-delimited) = "hi"
+        let data: &[u8] = &[0x08, 0x96, 0x01, 0x12, 0x02, b'h', b'i'];
+        let value = body_format::decode(body_format::BodyFormat::Protobuf, None, data).unwrap();
+        assert_eq!(value.get_field("1"), Some(&body_format::Value::Number(150.0)));
+        assert_eq!(value.get_field("2"), Some(&body_format::Value::Str("hi".to_string())));
+    }
+
+    #[test]
+    fn test_route_falls_back_to_default_on_undecodable_format() {
+        let config = FilterConfig::new(
+            r#"{"routes": [{"expr": "body.json.method == \"echo2\"", "route_to": "echo2"}]}"#,
+        );
+        let body = decode_body(None, &[("content-type".to_string(), "application/octet-stream".to_string())], b"\x00\x01");
+        assert!(body.is_none());
+        let ctx = expr::Context {
+            body: body.as_ref(),
+            body_len: 2,
+            headers: &[],
+        };
+        let filter = filter_from(&config);
+        assert_eq!(filter.resolve_rule(&ctx).map(|r| r.route_to.as_str()), None);
+    }
+
+    #[test]
+    fn test_callout_compiles_path_template_and_response_fields() {
+        let config = FilterConfig::new(
+            r#"{"callout": {
+                "cluster": "authz",
+                "path_template": "/authz/{user.id}",
+                "response_headers": [{"from": "x-tier", "to": "x-user-tier"}],
+                "response_fields": [{"expr": "body.json.route", "header": "x-route-to"}],
+                "on_failure": {"action": "reject", "status": 403}
+            }}"#,
+        );
+        let callout = config.compiled_callout.as_ref().expect("callout should compile");
+        assert_eq!(callout.cluster, "authz");
+        assert_eq!(callout.response_fields.len(), 1);
+        match &callout.on_failure {
+            CalloutFailureAction::Reject { status } => assert_eq!(*status, 403),
+            CalloutFailureAction::Continue => panic!("expected Reject"),
+        }
+    }
+
+    #[test]
+    fn test_callout_dispatch_failure_honors_fail_closed_policy() {
+        // MockEnvoyHttpFilter::send_http_callout always returns false, simulating
+        // a synchronous dispatch failure (e.g. unknown cluster). With
+        // on_failure: reject configured, that must reject the request rather
+        // than silently falling open.
+        let config = FilterConfig::new(
+            r#"{
+                "body_format": "json",
+                "callout": {
+                    "cluster": "authz",
+                    "path_template": "/authz",
+                    "on_failure": {"action": "reject", "status": 403}
+                }
+            }"#,
+        );
+        let mut filter = filter_from(&config);
+        let mut envoy = MockEnvoyHttpFilter::default();
+        envoy.request_body = br#"{"user": {"id": "42"}}"#.to_vec();
+
+        assert_eq!(
+            filter.on_request_body(&mut envoy, true),
+            abi::envoy_dynamic_module_type_on_http_filter_request_body_status::StopIterationNoBuffer
+        );
+    }
+
+    #[test]
+    fn test_callout_defaults_to_fail_open() {
+        let config = FilterConfig::new(r#"{"callout": {"cluster": "authz", "path_template": "/authz"}}"#);
+        let callout = config.compiled_callout.as_ref().expect("callout should compile");
+        assert!(matches!(callout.on_failure, CalloutFailureAction::Continue));
+        assert_eq!(callout.timeout_ms, default_callout_timeout_ms());
+    }
+
+    #[test]
+    fn test_resolve_path_template_substitutes_body_fields() {
+        let segments = compile_path_template("/authz/{user.id}/check").expect("should compile");
+        let body = json_body(r#"{"user": {"id": "42"}}"#);
+        let ctx = expr::Context {
+            body: Some(&body),
+            body_len: 0,
+            headers: &[],
+        };
+        assert_eq!(resolve_path_template(&segments, &ctx), "/authz/42/check");
+    }
+
+    #[test]
+    fn test_resolve_path_template_percent_encodes_path_control_characters() {
+        // A body-controlled field must not be able to splice extra path segments
+        // (e.g. "../") into the callout's :path.
+        let segments = compile_path_template("/authz/{user.id}").expect("should compile");
+        let body = json_body(r#"{"user": {"id": "../../admin"}}"#);
+        let ctx = expr::Context {
+            body: Some(&body),
+            body_len: 0,
+            headers: &[],
+        };
+        assert_eq!(resolve_path_template(&segments, &ctx), "/authz/..%2F..%2Fadmin");
+    }
+
+    #[test]
+    fn test_resolve_rule_exposes_matched_expr_src_for_decision_log() {
+        let config = FilterConfig::new(
+            r#"{"routes": [{"expr": "body.json.method == \"echo2\"", "route_to": "echo2"}]}"#,
+        );
+        let body = json_body(r#"{"method": "echo2"}"#);
+        let ctx = expr::Context {
+            body: Some(&body),
+            body_len: 10,
+            headers: &[],
+        };
+        let filter = filter_from(&config);
+        let matched = filter.resolve_rule(&ctx).expect("rule should match");
+        assert_eq!(matched.expr_src, "body.json.method == \"echo2\"");
+    }
+
+    #[test]
+    fn test_resolve_path_template_drops_unresolvable_field() {
+        let segments = compile_path_template("/authz/{missing.field}").expect("should compile");
+        let body = json_body(r#"{"user": {"id": "42"}}"#);
+        let ctx = expr::Context {
+            body: Some(&body),
+            body_len: 0,
+            headers: &[],
+        };
+        assert_eq!(resolve_path_template(&segments, &ctx), "/authz/");
+    }
+}
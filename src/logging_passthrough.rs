@@ -1,13 +1,59 @@
 use envoy_proxy_dynamic_modules_rust_sdk::*;
 use serde::{Deserialize, Serialize};
 
+/// Verbosity for the structured logging emitted by this filter. Variants are
+/// ordered from least to most verbose, so a configured level also permits every
+/// less-verbose level (e.g. `debug` still shows `error`/`info` lines).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Info,
+    Debug,
+}
+
+fn default_body_preview_bytes() -> usize {
+    200
+}
+
 /// This implements the [`envoy_proxy_dynamic_modules_rust_sdk::HttpFilterConfig`] trait.
 ///
 /// The trait corresponds to a Envoy filter chain configuration.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct FilterConfig {
+    /// Shorthand for `log_level: debug`; only consulted when `log_level` is unset.
     #[serde(default)]
     debug: bool,
+    /// Verbosity for logging. Defaults to `info`, or `debug` if `debug: true` and
+    /// `log_level` is unset.
+    #[serde(default)]
+    log_level: Option<LogLevel>,
+    /// Header names and dotted JSON field paths (e.g. `"user.token"`) whose
+    /// values are masked before logging.
+    #[serde(default)]
+    redact: Vec<String>,
+    /// Maximum number of bytes of a request/response body included in a logged
+    /// preview.
+    #[serde(default = "default_body_preview_bytes")]
+    body_preview_bytes: usize,
+}
+
+impl FilterConfig {
+    fn empty() -> Self {
+        FilterConfig {
+            debug: false,
+            log_level: None,
+            redact: Vec::new(),
+            body_preview_bytes: default_body_preview_bytes(),
+        }
+    }
+
+    /// Resolves the configured verbosity: `log_level` if set, else `debug` as a
+    /// `Debug`/`Info` shorthand.
+    fn effective_log_level(&self) -> LogLevel {
+        self.log_level.unwrap_or(if self.debug { LogLevel::Debug } else { LogLevel::Info })
+    }
 }
 
 impl FilterConfig {
@@ -16,100 +62,211 @@ impl FilterConfig {
     /// filter_config is the filter config from the Envoy config here:
     /// https://www.envoyproxy.io/docs/envoy/latest/api-v3/extensions/dynamic_modules/v3/dynamic_modules.proto#envoy-v3-api-msg-extensions-dynamic-modules-v3-dynamicmoduleconfig
     pub fn new(filter_config: &str) -> Self {
-        eprintln!("[BODY_TO_HEADER] FilterConfig created with config: {}", filter_config);
-        
-        let config = if filter_config.trim().is_empty() {
-            // Default config if empty
-            FilterConfig { debug: false }
-        } else {
-            match serde_json::from_str::<FilterConfig>(filter_config) {
-                Ok(cfg) => {
-                    eprintln!("[BODY_TO_HEADER] Parsed config successfully: debug={}", cfg.debug);
-                    cfg
-                }
-                Err(err) => {
-                    eprintln!("[BODY_TO_HEADER] Error parsing filter config, using defaults: {}", err);
-                    FilterConfig { debug: false }
-                }
+        if filter_config.trim().is_empty() {
+            return FilterConfig::empty();
+        }
+
+        match serde_json::from_str::<FilterConfig>(filter_config) {
+            Ok(cfg) => {
+                log_line(
+                    cfg.effective_log_level(),
+                    LogLevel::Info,
+                    "config",
+                    &[("status", "parsed".to_string()), ("log_level", format!("{:?}", cfg.effective_log_level()))],
+                );
+                cfg
             }
-        };
-        
-        config
+            Err(err) => {
+                log_line(LogLevel::Error, LogLevel::Error, "config", &[
+                    ("status", "parse_error".to_string()),
+                    ("error", err.to_string()),
+                ]);
+                FilterConfig::empty()
+            }
+        }
     }
 }
 
 impl<EC: EnvoyHttpFilterConfig, EHF: EnvoyHttpFilter> HttpFilterConfig<EC, EHF> for FilterConfig {
     /// This is called for each new HTTP filter.
     fn new_http_filter(&mut self, _envoy: &mut EC) -> Box<dyn HttpFilter<EHF>> {
-        eprintln!("[BODY_TO_HEADER] Creating new HTTP filter instance");
-        Box::new(Filter::new())
+        Box::new(Filter::new(self.effective_log_level(), self.redact.clone(), self.body_preview_bytes))
+    }
+}
+
+/// Emits a single structured `key=value` log line, gated by `configured` allowing
+/// `at`. Used both by [`FilterConfig::new`] (before a [`Filter`] exists to log
+/// through) and by [`Filter::log`].
+fn log_line(configured: LogLevel, at: LogLevel, stage: &str, fields: &[(&str, String)]) {
+    if at > configured {
+        return;
+    }
+    let mut line = format!("[BODY_TO_HEADER] level={:?} stage={}", at, stage);
+    for (key, value) in fields {
+        line.push(' ');
+        line.push_str(key);
+        line.push('=');
+        line.push_str(value);
+    }
+    eprintln!("{}", line);
+}
+
+/// Truncates `s` to at most `max_bytes` bytes, backing off to the nearest
+/// preceding UTF-8 char boundary so the result is always valid `str`.
+fn truncate_to_bytes(s: &str, max_bytes: usize) -> &str {
+    if s.len() <= max_bytes {
+        return s;
+    }
+    let mut end = max_bytes;
+    while end > 0 && !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+fn redact_json_path(value: &mut serde_json::Value, segments: &[&str]) {
+    match segments {
+        [] => {}
+        [last] => {
+            if let Some(field) = value.as_object_mut().and_then(|obj| obj.get_mut(*last)) {
+                *field = serde_json::Value::String("***".to_string());
+            }
+        }
+        [head, rest @ ..] => {
+            if let Some(next) = value.as_object_mut().and_then(|obj| obj.get_mut(*head)) {
+                redact_json_path(next, rest);
+            }
+        }
     }
 }
 
+/// Builds a body preview: if `body_str` parses as JSON, every dotted path in
+/// `redact` is masked before re-serializing; otherwise the raw text is used as
+/// is. Either way, the result is truncated to `max_bytes`.
+fn redact_body_preview(body_str: &str, redact: &[String], max_bytes: usize) -> String {
+    let text = match serde_json::from_str::<serde_json::Value>(body_str) {
+        Ok(mut json) => {
+            for path in redact {
+                redact_json_path(&mut json, &path.split('.').collect::<Vec<_>>());
+            }
+            serde_json::to_string(&json).unwrap_or_else(|_| body_str.to_string())
+        }
+        Err(_) => body_str.to_string(),
+    };
+    truncate_to_bytes(&text, max_bytes).to_string()
+}
+
 /// This implements the [`envoy_proxy_dynamic_modules_rust_sdk::HttpFilter`] trait.
 ///
 /// This is a passthrough filter that logs at each stage of request processing.
 pub struct Filter {
     request_id: String,
+    log_level: LogLevel,
+    redact: Vec<String>,
+    body_preview_bytes: usize,
 }
 
 impl Filter {
-    pub fn new() -> Self {
+    pub fn new(log_level: LogLevel, redact: Vec<String>, body_preview_bytes: usize) -> Self {
         let request_id = format!("req_{}", std::process::id());
-        eprintln!("[BODY_TO_HEADER] [{}] Filter created", request_id);
-        Self { request_id }
+        let filter = Self {
+            request_id,
+            log_level,
+            redact,
+            body_preview_bytes,
+        };
+        filter.log(LogLevel::Debug, "filter", &[("event", "created".to_string())]);
+        filter
+    }
+
+    /// Emits a single structured `key=value` log line if `level` is enabled by
+    /// `self.log_level`.
+    fn log(&self, level: LogLevel, stage: &str, fields: &[(&str, String)]) {
+        if level > self.log_level {
+            return;
+        }
+        let mut line = format!("[BODY_TO_HEADER] level={:?} request_id={} stage={}", level, self.request_id, stage);
+        for (key, value) in fields {
+            line.push(' ');
+            line.push_str(key);
+            line.push('=');
+            line.push_str(value);
+        }
+        eprintln!("{}", line);
+    }
+
+    fn redacted_header_value(&self, name: &str, value: &str) -> String {
+        if self.redact.iter().any(|r| r.eq_ignore_ascii_case(name)) {
+            "***".to_string()
+        } else {
+            value.to_string()
+        }
     }
 
     fn log_headers(&self, stage: &str, headers: &[(EnvoyBuffer, EnvoyBuffer)]) {
-        eprintln!("[BODY_TO_HEADER] [{}] === {} HEADERS ===", self.request_id, stage);
         for (key, value) in headers {
             if let (Ok(key_str), Ok(value_str)) = (
                 std::str::from_utf8(key.as_slice()),
                 std::str::from_utf8(value.as_slice())
             ) {
-                eprintln!("[BODY_TO_HEADER] [{}]   {}: {}", self.request_id, key_str, value_str);
+                let value_str = self.redacted_header_value(key_str, value_str);
+                self.log(LogLevel::Info, "headers", &[
+                    ("phase", stage.to_string()),
+                    ("name", key_str.to_string()),
+                    ("value", value_str),
+                ]);
             }
         }
-        eprintln!("[BODY_TO_HEADER] [{}] ========================", self.request_id);
     }
 
     fn log_body(&self, stage: &str, body: Option<&[u8]>, end_of_stream: bool) {
-        eprintln!("[BODY_TO_HEADER] [{}] === {} BODY ===", self.request_id, stage);
-        eprintln!("[BODY_TO_HEADER] [{}] End of stream: {}", self.request_id, end_of_stream);
-        if let Some(body_data) = body {
-            eprintln!("[BODY_TO_HEADER] [{}] Body length: {} bytes", self.request_id, body_data.len());
-            if body_data.len() > 0 {
-                match std::str::from_utf8(body_data) {
-                    Ok(body_str) => {
-                        let preview = if body_str.len() > 200 {
-                            format!("{}...", &body_str[..200])
-                        } else {
-                            body_str.to_string()
-                        };
-                        eprintln!("[BODY_TO_HEADER] [{}] Body preview: {}", self.request_id, preview);
-                    }
-                    Err(_) => {
-                        eprintln!("[BODY_TO_HEADER] [{}] Body contains non-UTF8 data", self.request_id);
-                    }
-                }
+        let Some(body_data) = body else {
+            self.log(LogLevel::Info, "body", &[
+                ("phase", stage.to_string()),
+                ("end_of_stream", end_of_stream.to_string()),
+                ("bytes", "0".to_string()),
+            ]);
+            return;
+        };
+
+        self.log(LogLevel::Info, "body", &[
+            ("phase", stage.to_string()),
+            ("end_of_stream", end_of_stream.to_string()),
+            ("bytes", body_data.len().to_string()),
+        ]);
+
+        if body_data.is_empty() {
+            return;
+        }
+
+        // The payload preview itself is gated at debug: production deployments
+        // should see that a body arrived, but not dump its (possibly sensitive)
+        // contents by default.
+        match std::str::from_utf8(body_data) {
+            Ok(body_str) => {
+                let preview = redact_body_preview(body_str, &self.redact, self.body_preview_bytes);
+                self.log(LogLevel::Debug, "body", &[("phase", stage.to_string()), ("preview", preview)]);
+            }
+            Err(_) => {
+                self.log(LogLevel::Debug, "body", &[("phase", stage.to_string()), ("preview", "<non-utf8>".to_string())]);
             }
-        } else {
-            eprintln!("[BODY_TO_HEADER] [{}] No body data", self.request_id);
         }
-        eprintln!("[BODY_TO_HEADER] [{}] =================", self.request_id);
     }
 
     fn log_trailers(&self, stage: &str, trailers: &[(EnvoyBuffer, EnvoyBuffer)]) {
-        eprintln!("[BODY_TO_HEADER] [{}] === {} TRAILERS ===", self.request_id, stage);
         for (key, value) in trailers {
             if let (Ok(key_str), Ok(value_str)) = (
                 std::str::from_utf8(key.as_slice()),
                 std::str::from_utf8(value.as_slice())
             ) {
-                eprintln!("[BODY_TO_HEADER] [{}]   {}: {}", self.request_id, key_str, value_str);
+                let value_str = self.redacted_header_value(key_str, value_str);
+                self.log(LogLevel::Info, "trailers", &[
+                    ("phase", stage.to_string()),
+                    ("name", key_str.to_string()),
+                    ("value", value_str),
+                ]);
             }
         }
-        eprintln!("[BODY_TO_HEADER] [{}] ==========================", self.request_id);
     }
 }
 
@@ -120,11 +277,11 @@ impl<EHF: EnvoyHttpFilter> HttpFilter<EHF> for Filter {
         envoy_filter: &mut EHF,
         end_of_stream: bool,
     ) -> abi::envoy_dynamic_module_type_on_http_filter_request_headers_status {
-        eprintln!("[BODY_TO_HEADER] [{}] on_request_headers called (end_of_stream: {})", self.request_id, end_of_stream);
-        
+        self.log(LogLevel::Debug, "request_headers", &[("end_of_stream", end_of_stream.to_string())]);
+
         let headers = envoy_filter.get_request_headers();
         self.log_headers("REQUEST", &headers);
-        
+
         abi::envoy_dynamic_module_type_on_http_filter_request_headers_status::Continue
     }
 
@@ -133,8 +290,8 @@ impl<EHF: EnvoyHttpFilter> HttpFilter<EHF> for Filter {
         envoy_filter: &mut EHF,
         end_of_stream: bool,
     ) -> abi::envoy_dynamic_module_type_on_http_filter_request_body_status {
-        eprintln!("[BODY_TO_HEADER] [{}] on_request_body called (end_of_stream: {})", self.request_id, end_of_stream);
-        
+        self.log(LogLevel::Debug, "request_body", &[("end_of_stream", end_of_stream.to_string())]);
+
         if let Some(body_buffers) = envoy_filter.get_request_body() {
             // Collect body data from all buffers
             let mut body_data = Vec::new();
@@ -145,7 +302,7 @@ impl<EHF: EnvoyHttpFilter> HttpFilter<EHF> for Filter {
         } else {
             self.log_body("REQUEST", None, end_of_stream);
         }
-        
+
         abi::envoy_dynamic_module_type_on_http_filter_request_body_status::Continue
     }
 
@@ -153,11 +310,11 @@ impl<EHF: EnvoyHttpFilter> HttpFilter<EHF> for Filter {
         &mut self,
         envoy_filter: &mut EHF,
     ) -> abi::envoy_dynamic_module_type_on_http_filter_request_trailers_status {
-        eprintln!("[BODY_TO_HEADER] [{}] on_request_trailers called", self.request_id);
-        
+        self.log(LogLevel::Debug, "request_trailers", &[]);
+
         let trailers = envoy_filter.get_request_trailers();
         self.log_trailers("REQUEST", &trailers);
-        
+
         abi::envoy_dynamic_module_type_on_http_filter_request_trailers_status::Continue
     }
 
@@ -166,11 +323,11 @@ impl<EHF: EnvoyHttpFilter> HttpFilter<EHF> for Filter {
         envoy_filter: &mut EHF,
         end_of_stream: bool,
     ) -> abi::envoy_dynamic_module_type_on_http_filter_response_headers_status {
-        eprintln!("[BODY_TO_HEADER] [{}] on_response_headers called (end_of_stream: {})", self.request_id, end_of_stream);
-        
+        self.log(LogLevel::Debug, "response_headers", &[("end_of_stream", end_of_stream.to_string())]);
+
         let headers = envoy_filter.get_response_headers();
         self.log_headers("RESPONSE", &headers);
-        
+
         abi::envoy_dynamic_module_type_on_http_filter_response_headers_status::Continue
     }
 
@@ -179,8 +336,8 @@ impl<EHF: EnvoyHttpFilter> HttpFilter<EHF> for Filter {
         envoy_filter: &mut EHF,
         end_of_stream: bool,
     ) -> abi::envoy_dynamic_module_type_on_http_filter_response_body_status {
-        eprintln!("[BODY_TO_HEADER] [{}] on_response_body called (end_of_stream: {})", self.request_id, end_of_stream);
-        
+        self.log(LogLevel::Debug, "response_body", &[("end_of_stream", end_of_stream.to_string())]);
+
         if let Some(body_buffers) = envoy_filter.get_response_body() {
             // Collect body data from all buffers
             let mut body_data = Vec::new();
@@ -191,7 +348,7 @@ impl<EHF: EnvoyHttpFilter> HttpFilter<EHF> for Filter {
         } else {
             self.log_body("RESPONSE", None, end_of_stream);
         }
-        
+
         abi::envoy_dynamic_module_type_on_http_filter_response_body_status::Continue
     }
 
@@ -199,18 +356,18 @@ impl<EHF: EnvoyHttpFilter> HttpFilter<EHF> for Filter {
         &mut self,
         envoy_filter: &mut EHF,
     ) -> abi::envoy_dynamic_module_type_on_http_filter_response_trailers_status {
-        eprintln!("[BODY_TO_HEADER] [{}] on_response_trailers called", self.request_id);
-        
+        self.log(LogLevel::Debug, "response_trailers", &[]);
+
         let trailers = envoy_filter.get_response_trailers();
         self.log_trailers("RESPONSE", &trailers);
-        
+
         abi::envoy_dynamic_module_type_on_http_filter_response_trailers_status::Continue
     }
 }
 
 impl Drop for Filter {
     fn drop(&mut self) {
-        eprintln!("[BODY_TO_HEADER] [{}] Filter dropped", self.request_id);
+        self.log(LogLevel::Debug, "filter", &[("event", "dropped".to_string())]);
     }
 }
 
@@ -222,20 +379,53 @@ mod tests {
     fn test_filter_config() {
         // Test with valid JSON config
         let config = FilterConfig::new(r#"{"debug": true}"#);
-        assert_eq!(config.debug, true);
-        
+        assert!(config.debug);
+        assert_eq!(config.effective_log_level(), LogLevel::Debug);
+
         // Test with empty config (uses defaults)
         let config = FilterConfig::new("");
-        assert_eq!(config.debug, false);
-        
+        assert!(!config.debug);
+        assert_eq!(config.effective_log_level(), LogLevel::Info);
+
         // Test with invalid JSON (uses defaults)
         let config = FilterConfig::new("invalid json");
-        assert_eq!(config.debug, false);
+        assert!(!config.debug);
     }
 
     #[test]
     fn test_filter_creation() {
-        let filter = Filter::new();
+        let filter = Filter::new(LogLevel::Info, Vec::new(), default_body_preview_bytes());
         assert!(!filter.request_id.is_empty());
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_log_level_defaults_to_info_and_respects_explicit_value() {
+        let config = FilterConfig::new(r#"{}"#);
+        assert_eq!(config.effective_log_level(), LogLevel::Info);
+
+        let config = FilterConfig::new(r#"{"log_level": "error"}"#);
+        assert_eq!(config.effective_log_level(), LogLevel::Error);
+
+        // An explicit log_level takes precedence over the debug shorthand.
+        let config = FilterConfig::new(r#"{"debug": true, "log_level": "error"}"#);
+        assert_eq!(config.effective_log_level(), LogLevel::Error);
+    }
+
+    #[test]
+    fn test_redact_body_preview_masks_configured_json_paths() {
+        let preview = redact_body_preview(
+            r#"{"user": {"token": "secret", "id": "42"}}"#,
+            &["user.token".to_string()],
+            1024,
+        );
+        assert!(preview.contains("\"token\":\"***\""));
+        assert!(preview.contains("\"id\":\"42\""));
+    }
+
+    #[test]
+    fn test_redact_body_preview_truncates_on_char_boundary() {
+        let preview = redact_body_preview("héllo world", &[], 2);
+        assert!(preview.len() <= 2);
+        assert!(std::str::from_utf8(preview.as_bytes()).is_ok());
+    }
+}